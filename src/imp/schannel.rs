@@ -1,7 +1,8 @@
 extern crate schannel;
 
-use self::schannel::cert_context::{CertContext, HashAlgorithm};
+use self::schannel::cert_context::{CertContext, HashAlgorithm, KeySpec};
 use self::schannel::cert_store::{CertAdd, CertStore, Memory, PfxImportOptions};
+use self::schannel::crypt_prov::{AcquireOptions, ProviderType};
 use self::schannel::schannel_cred::{Algorithm, Direction, Protocol, SchannelCred};
 use self::schannel::tls_stream;
 use std::collections::VecDeque;
@@ -9,6 +10,7 @@ use std::error;
 use std::fmt;
 use std::io;
 use std::str;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime};
 
@@ -65,6 +67,58 @@ impl From<TlsHashAlgorithm> for Algorithm {
     }
 }
 
+fn protocol_from_schannel(protocol: Protocol) -> Option<::Protocol> {
+    match protocol {
+        Protocol::Ssl3 => Some(::Protocol::Sslv3),
+        Protocol::Tls10 => Some(::Protocol::Tlsv10),
+        Protocol::Tls11 => Some(::Protocol::Tlsv11),
+        Protocol::Tls12 => Some(::Protocol::Tlsv12),
+    }
+}
+
+fn bulk_encryption_algorithm_from_schannel(alg: Algorithm) -> Option<TlsBulkEncryptionAlgorithm> {
+    match alg {
+        Algorithm::Aes128 => Some(TlsBulkEncryptionAlgorithm::Aes128),
+        Algorithm::Aes256 => Some(TlsBulkEncryptionAlgorithm::Aes256),
+        Algorithm::Des => Some(TlsBulkEncryptionAlgorithm::Des),
+        Algorithm::Rc2 => Some(TlsBulkEncryptionAlgorithm::Rc2),
+        Algorithm::Rc4 => Some(TlsBulkEncryptionAlgorithm::Rc4),
+        Algorithm::TripleDes => Some(TlsBulkEncryptionAlgorithm::TripleDes),
+        _ => None,
+    }
+}
+
+fn key_exchange_algorithm_from_schannel(alg: Algorithm) -> Option<TlsKeyExchangeAlgorithm> {
+    match alg {
+        Algorithm::DhEphem => Some(TlsKeyExchangeAlgorithm::Dhe),
+        Algorithm::EcdhEphem => Some(TlsKeyExchangeAlgorithm::Ecdhe),
+        Algorithm::RsaKeyx => Some(TlsKeyExchangeAlgorithm::Rsa),
+        _ => None,
+    }
+}
+
+fn hash_algorithm_from_schannel(alg: Algorithm) -> Option<TlsHashAlgorithm> {
+    match alg {
+        Algorithm::Md5 => Some(TlsHashAlgorithm::Md5),
+        Algorithm::Sha1 => Some(TlsHashAlgorithm::Sha1),
+        Algorithm::Sha256 => Some(TlsHashAlgorithm::Sha256),
+        Algorithm::Sha384 => Some(TlsHashAlgorithm::Sha384),
+        _ => None,
+    }
+}
+
+/// The cipher suite negotiated for a `TlsStream`.
+///
+/// A component is `None` when schannel reports an algorithm this crate doesn't have a matching
+/// public enum variant for (e.g. an AEAD suite with no separate MAC), rather than failing the
+/// whole query over one unrecognized part.
+#[derive(Debug, Clone, Copy)]
+pub struct CipherSuite {
+    pub bulk_encryption_algorithm: Option<TlsBulkEncryptionAlgorithm>,
+    pub key_exchange_algorithm: Option<TlsKeyExchangeAlgorithm>,
+    pub hash_algorithm: Option<TlsHashAlgorithm>,
+}
+
 fn expand_algorithms(cipher_suites: &CipherSuiteSet) -> Vec<Algorithm> {
     let mut ret = vec![];
     ret.extend(
@@ -87,6 +141,11 @@ fn expand_algorithms(cipher_suites: &CipherSuiteSet) -> Vec<Algorithm> {
 }
 
 const SEC_E_NO_CREDENTIALS: u32 = 0x8009030E;
+const CRYPT_E_NO_REVOCATION_CHECK: u32 = 0x80092012;
+const CRYPT_E_REVOCATION_OFFLINE: u32 = 0x80092013;
+const CERT_E_UNTRUSTEDROOT: u32 = 0x800B0109;
+const CERT_E_REVOKED: u32 = 0x800B010C;
+const CERT_E_REVOCATION_FAILURE: u32 = 0x800B010D;
 
 static PROTOCOLS: &'static [Protocol] = &[
     Protocol::Ssl3,
@@ -119,6 +178,28 @@ fn convert_protocols(min: Option<::Protocol>, max: Option<::Protocol>) -> &'stat
     protocols
 }
 
+// Puts `leaf` and `chain` into a shared in-memory store and hands back the copy of `leaf` that
+// comes out of that store, so that it carries the intermediates as its associated certificate
+// store. Schannel consults a credential's associated store to decide what chain to send during
+// the handshake, so this is what makes `TlsAcceptor` advertise the full chain instead of just
+// the leaf.
+fn attach_chain(leaf: &CertContext, chain: &[CertContext]) -> io::Result<CertContext> {
+    if chain.is_empty() {
+        return Ok(leaf.clone());
+    }
+
+    let mut store = Memory::new()?.into_store();
+    store.add_cert(leaf, CertAdd::ReplaceExisting)?;
+    for cert in chain {
+        store.add_cert(cert, CertAdd::ReplaceExisting)?;
+    }
+
+    Ok(store
+        .certs()
+        .find(|cert| cert == leaf)
+        .unwrap_or_else(|| leaf.clone()))
+}
+
 pub struct Error(io::Error);
 
 impl error::Error for Error {
@@ -148,23 +229,31 @@ impl From<io::Error> for Error {
 #[derive(Clone)]
 pub struct Identity {
     cert: CertContext,
+    chain: Vec<CertContext>,
+    // Keeps the CSP keyset created by `from_pkcs8` alive (and deleted once the last clone of
+    // this `Identity` goes away); `None` for identities loaded from a PKCS #12 archive, which
+    // don't create a keyset of their own.
+    key_container: Option<Arc<KeyContainerGuard>>,
 }
 
 impl Identity {
     pub fn from_pkcs12(buf: &[u8], pass: &str) -> Result<Identity, Error> {
         let store = PfxImportOptions::new().password(pass).import(buf)?;
         let mut identity = None;
+        let mut chain = vec![];
 
         for cert in store.certs() {
-            if cert
-                .private_key()
-                .silent(true)
-                .compare_key(true)
-                .acquire()
-                .is_ok()
+            if identity.is_none()
+                && cert
+                    .private_key()
+                    .silent(true)
+                    .compare_key(true)
+                    .acquire()
+                    .is_ok()
             {
                 identity = Some(cert);
-                break;
+            } else {
+                chain.push(cert);
             }
         }
 
@@ -178,8 +267,189 @@ impl Identity {
             }
         };
 
-        Ok(Identity { cert: identity })
+        Ok(Identity {
+            cert: identity,
+            chain,
+            key_container: None,
+        })
+    }
+
+    /// Imports an identity from a PEM certificate chain and a PKCS#8-encoded private key.
+    ///
+    /// Only RSA private keys are supported: the algorithm is read from the key's PKCS#8
+    /// `AlgorithmIdentifier` up front, so an EC key is rejected with a clear error from this
+    /// function rather than failing deep inside CSP import with an opaque, low-level error.
+    pub fn from_pkcs8(cert_pem: &[u8], key_pem: &[u8]) -> Result<Identity, Error> {
+        let cert_chain = str::from_utf8(cert_pem).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "PEM representation contains non-UTF-8 bytes",
+            )
+        })?;
+        let key_pem = str::from_utf8(key_pem).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "PEM representation contains non-UTF-8 bytes",
+            )
+        })?;
+
+        if pkcs8_key_algorithm(key_pem)? != Pkcs8KeyAlgorithm::Rsa {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "only RSA private keys are supported by Identity::from_pkcs8 on this backend",
+            ).into());
+        }
+
+        let mut certs = split_pem_certificates(cert_chain)
+            .into_iter()
+            .map(CertContext::from_pem)
+            .collect::<Result<VecDeque<_>, _>>()?;
+
+        let leaf = match certs.pop_front() {
+            Some(leaf) => leaf,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "no certificate found in the provided PEM",
+                ).into());
+            }
+        };
+
+        // Import the key into a throwaway container so we can bind it to the leaf certificate
+        // below; schannel has no notion of a "detached" private key outside of a CSP container.
+        // The container must be freshly created (`new_keyset`) since `generate_container_name`
+        // hands back a name nothing has acquired before.
+        let container_name = generate_container_name();
+        let mut options = AcquireOptions::new();
+        options.container(&container_name).new_keyset(true);
+        let container = options.acquire(ProviderType::rsa_full())?;
+        container.import().import_pkcs8_pem(key_pem)?;
+        let key_container = Arc::new(KeyContainerGuard(container_name.clone()));
+
+        leaf.set_key_prov_info(&container_name, ProviderType::rsa_full())?;
+        leaf.set_key_spec(KeySpec::key_exchange())?;
+
+        if leaf
+            .private_key()
+            .silent(true)
+            .compare_key(true)
+            .acquire()
+            .is_err()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "private key does not match certificate",
+            ).into());
+        }
+
+        Ok(Identity {
+            cert: leaf,
+            chain: certs.into_iter().collect(),
+            key_container: Some(key_container),
+        })
+    }
+}
+
+// The CSP keyset backing an `Identity::from_pkcs8` private key is persisted on disk by
+// `CryptAcquireContext`, so it must be explicitly torn down once nothing references it anymore
+// or every call to `from_pkcs8` leaks a keyset for the lifetime of the machine.
+struct KeyContainerGuard(String);
+
+impl Drop for KeyContainerGuard {
+    fn drop(&mut self) {
+        let mut options = AcquireOptions::new();
+        options.container(&self.0).delete_keyset(true);
+        let _ = options.acquire(ProviderType::rsa_full());
+    }
+}
+
+// Splits a PEM document containing zero or more `CERTIFICATE` blocks into the individual PEM
+// blocks so that each can be fed to `CertContext::from_pem` on its own.
+fn split_pem_certificates(pem: &str) -> Vec<&str> {
+    const BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+    const END: &str = "-----END CERTIFICATE-----";
+
+    let mut blocks = vec![];
+    let mut rest = pem;
+    while let Some(start) = rest.find(BEGIN) {
+        let candidate = &rest[start..];
+        match candidate.find(END) {
+            Some(end) => {
+                let end = end + END.len();
+                blocks.push(&candidate[..end]);
+                rest = &candidate[end..];
+            }
+            None => break,
+        }
     }
+    blocks
+}
+
+#[derive(PartialEq)]
+enum Pkcs8KeyAlgorithm {
+    Rsa,
+    Ec,
+}
+
+// DER encodings (tag + length + value) of the `rsaEncryption` and `id-ecPublicKey` OIDs that show
+// up in a PKCS#8 `PrivateKeyInfo`'s `AlgorithmIdentifier`.
+const RSA_ENCRYPTION_OID: &[u8] = &[0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+const EC_PUBLIC_KEY_OID: &[u8] = &[0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+
+// Decodes the base64 body of a single PEM block, ignoring whatever `-----BEGIN/END-----` label
+// wraps it, without pulling in a base64 dependency just for this one call site.
+fn decode_pem_body(pem: &str) -> Result<Vec<u8>, Error> {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut bits = 0u32;
+    let mut nbits = 0u32;
+    let mut out = vec![];
+    for line in pem.lines() {
+        if line.starts_with("-----") {
+            continue;
+        }
+        for c in line.bytes() {
+            if c == b'=' {
+                continue;
+            }
+            let value = ALPHABET.iter().position(|&b| b == c).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "invalid base64 in PEM body")
+            })?;
+            bits = (bits << 6) | value as u32;
+            nbits += 6;
+            if nbits >= 8 {
+                nbits -= 8;
+                out.push((bits >> nbits) as u8);
+            }
+        }
+    }
+    Ok(out)
+}
+
+// Figures out whether a PKCS#8 `PrivateKeyInfo` holds an RSA or an EC key by looking for the
+// corresponding `AlgorithmIdentifier` OID in the decoded DER, rather than writing a full DER
+// parser for the one field we care about.
+fn pkcs8_key_algorithm(key_pem: &str) -> Result<Pkcs8KeyAlgorithm, Error> {
+    let der = decode_pem_body(key_pem)?;
+    if der.windows(RSA_ENCRYPTION_OID.len()).any(|w| w == RSA_ENCRYPTION_OID) {
+        Ok(Pkcs8KeyAlgorithm::Rsa)
+    } else if der.windows(EC_PUBLIC_KEY_OID.len()).any(|w| w == EC_PUBLIC_KEY_OID) {
+        Ok(Pkcs8KeyAlgorithm::Ec)
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "unrecognized PKCS#8 private key algorithm",
+        ).into())
+    }
+}
+
+static NEXT_CONTAINER_ID: AtomicUsize = AtomicUsize::new(0);
+
+// Generates a container name that is unique for the lifetime of the process so that concurrent
+// calls to `Identity::from_pkcs8` don't stomp on each other's ephemeral key containers.
+fn generate_container_name() -> String {
+    let id = NEXT_CONTAINER_ID.fetch_add(1, Ordering::SeqCst);
+    format!("rust-native-tls-{}-{}", std::process::id(), id)
 }
 
 #[derive(Clone)]
@@ -278,6 +548,8 @@ pub struct TlsConnector {
     disable_built_in_roots: bool,
     alpn: Vec<Vec<u8>>,
     supported_algorithms: Vec<Algorithm>,
+    check_revocation: bool,
+    revocation_soft_fail: bool,
 }
 
 impl TlsConnector {
@@ -287,6 +559,14 @@ impl TlsConnector {
         for cert in &builder.root_certificates {
             roots.add_cert(&(cert.0).0, CertAdd::ReplaceExisting)?;
         }
+        // `CertGetCertificateChain` searches whatever additional store it's handed (the same
+        // `roots` store passed to `cert_store` below) for CRLs as well as certificates, so
+        // dropping offline CRLs in alongside the trusted roots is enough to let `check_revocation`
+        // resolve revocation status locally instead of only through the system's online CRL/OCSP
+        // fetch.
+        for crl in &builder.offline_crls {
+            roots.add_crl(crl, CertAdd::ReplaceExisting)?;
+        }
 
         Ok(TlsConnector {
             cert,
@@ -304,6 +584,8 @@ impl TlsConnector {
                 Some(cipher_suites) => expand_algorithms(cipher_suites),
                 None => vec![],
             },
+            check_revocation: builder.check_revocation,
+            revocation_soft_fail: builder.revocation_soft_fail,
         })
     }
 
@@ -320,27 +602,53 @@ impl TlsConnector {
             .accept_invalid_hostnames(self.accept_invalid_hostnames);
         if self.accept_invalid_certs {
             builder.verify_callback(|_| Ok(()));
-        } else if self.disable_built_in_roots {
+        } else if self.disable_built_in_roots || self.check_revocation {
+            let disable_built_in_roots = self.disable_built_in_roots;
             let roots_copy = self.roots.clone();
+            let check_revocation = self.check_revocation;
+            let revocation_soft_fail = self.revocation_soft_fail;
             builder.verify_callback(move |res| {
                 if let Err(err) = res.result() {
-                    // Propagate previous error encountered during normal cert validation.
-                    return Err(err);
+                    let revocation_code = err.raw_os_error().map(|code| code as u32);
+
+                    if check_revocation && revocation_code == Some(CERT_E_REVOKED) {
+                        return Err(io::Error::new(
+                            io::ErrorKind::Other,
+                            "the server's certificate has been revoked",
+                        ));
+                    }
+
+                    // "Revocation status unknown" can surface under any of these codes
+                    // depending on why the check couldn't complete (no distribution point,
+                    // offline, or the OS skipping the check altogether).
+                    let is_soft_failable_revocation = check_revocation
+                        && revocation_soft_fail
+                        && (revocation_code == Some(CERT_E_REVOCATION_FAILURE)
+                            || revocation_code == Some(CRYPT_E_REVOCATION_OFFLINE)
+                            || revocation_code == Some(CRYPT_E_NO_REVOCATION_CHECK));
+
+                    // Propagate previous error encountered during normal cert validation,
+                    // unless it's a revocation status we've been told to tolerate.
+                    if !is_soft_failable_revocation {
+                        return Err(err);
+                    }
                 }
 
-                if let Some(chain) = res.chain() {
-                    if chain
-                        .certificates()
-                        .any(|cert| roots_copy.certs().any(|root_cert| root_cert == cert))
-                    {
-                        return Ok(());
+                if disable_built_in_roots {
+                    let matches_root = res.chain().map_or(false, |chain| {
+                        chain
+                            .certificates()
+                            .any(|cert| roots_copy.certs().any(|root_cert| root_cert == cert))
+                    });
+                    if !matches_root {
+                        return Err(io::Error::new(
+                            io::ErrorKind::Other,
+                            "unable to find any user-specified roots in the final cert chain",
+                        ));
                     }
                 }
 
-                Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    "unable to find any user-specified roots in the final cert chain",
-                ))
+                Ok(())
             });
         }
         if !self.alpn.is_empty() {
@@ -387,6 +695,9 @@ impl TlsConnector {
         if !self.supported_algorithms.is_empty() {
             builder.supported_algorithms(&self.supported_algorithms);
         }
+        if self.check_revocation {
+            builder.check_revocation(true);
+        }
         builder.acquire(Direction::Outbound)
     }
 
@@ -423,14 +734,30 @@ pub struct TlsAcceptor {
     cert: CertContext,
     min_protocol: Option<::Protocol>,
     max_protocol: Option<::Protocol>,
+    request_client_auth: bool,
+    require_client_auth: bool,
+    client_ca_roots: CertStore,
+    alpn: Vec<Vec<u8>>,
 }
 
 impl TlsAcceptor {
     pub fn new(builder: &TlsAcceptorBuilder) -> Result<TlsAcceptor, Error> {
+        let mut client_ca_roots = Memory::new()?.into_store();
+        for cert in &builder.client_root_certificates {
+            client_ca_roots.add_cert(&(cert.0).0, CertAdd::ReplaceExisting)?;
+        }
+
+        let identity = &builder.identity.0;
+        let cert = attach_chain(&identity.cert, &identity.chain)?;
+
         Ok(TlsAcceptor {
-            cert: builder.identity.0.cert.clone(),
+            cert,
             min_protocol: builder.min_protocol,
             max_protocol: builder.max_protocol,
+            request_client_auth: builder.request_client_auth,
+            require_client_auth: builder.require_client_auth,
+            client_ca_roots,
+            alpn: builder.alpn_protocols.clone(),
         })
     }
 
@@ -441,9 +768,77 @@ impl TlsAcceptor {
         let mut builder = SchannelCred::builder();
         builder.enabled_protocols(convert_protocols(self.min_protocol, self.max_protocol));
         builder.cert(self.cert.clone());
-        // FIXME we're probably missing the certificate chain?
         let cred = builder.acquire(Direction::Inbound)?;
-        match tls_stream::Builder::new().accept(cred, stream) {
+
+        let mut stream_builder = tls_stream::Builder::new();
+        if self.request_client_auth || self.require_client_auth {
+            stream_builder.request_client_certificate(true);
+
+            let have_client_ca_roots = self.client_ca_roots.certs().next().is_some();
+            if have_client_ca_roots {
+                // Give schannel the configured CA store so it can build the client's chain up
+                // to our own roots, which are very likely not in the machine's trust store.
+                stream_builder.cert_store(self.client_ca_roots.clone());
+            }
+
+            let require_client_auth = self.require_client_auth;
+            let client_ca_roots = self.client_ca_roots.clone();
+            stream_builder.verify_callback(move |res| {
+                match res.result() {
+                    Ok(()) => {}
+                    Err(ref e) if e.raw_os_error() == Some(SEC_E_NO_CREDENTIALS as i32) => {
+                        return if require_client_auth {
+                            Err(io::Error::new(
+                                io::ErrorKind::Other,
+                                "the client did not present a certificate",
+                            ))
+                        } else {
+                            Ok(())
+                        };
+                    }
+                    // A private client CA, configured above, is usually not in the machine's
+                    // trusted root store, so schannel reports the chain as untrusted even though
+                    // it built successfully. That's expected here; fall through to check the
+                    // chain's trust anchor against our own roots instead.
+                    Err(ref e)
+                        if have_client_ca_roots
+                            && e.raw_os_error() == Some(CERT_E_UNTRUSTEDROOT as i32) => {}
+                    Err(err) => return Err(err),
+                }
+
+                if !have_client_ca_roots {
+                    // No explicit CA store was configured, so defer to whatever the OS already
+                    // validated against the machine's trust store.
+                    return Ok(());
+                }
+
+                // The client never sends its own trust anchor, so look for it as the top of the
+                // chain schannel built (using the CA store above), not among the presented certs.
+                let trusted = match res.chain() {
+                    Some(chain) => chain
+                        .certificates()
+                        .last()
+                        .map_or(false, |anchor| client_ca_roots.certs().any(|root| root == anchor)),
+                    None => false,
+                };
+
+                if trusted {
+                    Ok(())
+                } else {
+                    Err(io::Error::new(
+                        io::ErrorKind::Other,
+                        "the client's certificate does not chain to any of the configured CA roots",
+                    ))
+                }
+            });
+        }
+        if !self.alpn.is_empty() {
+            stream_builder.request_application_protocols(
+                &self.alpn.iter().map(AsRef::as_ref).collect::<Vec<_>>(),
+            );
+        }
+
+        match stream_builder.accept(cred, stream) {
             Ok(s) => Ok(TlsStream(s)),
             Err(e) => Err(e.into()),
         }
@@ -485,6 +880,20 @@ impl<S: io::Read + io::Write> TlsStream<S> {
         Ok(self.0.negotiated_application_protocol()?)
     }
 
+    pub fn protocol_version(&self) -> Result<Option<::Protocol>, Error> {
+        let info = self.0.connection_info()?;
+        Ok(protocol_from_schannel(info.protocol))
+    }
+
+    pub fn negotiated_cipher_suite(&self) -> Result<CipherSuite, Error> {
+        let info = self.0.connection_info()?;
+        Ok(CipherSuite {
+            bulk_encryption_algorithm: bulk_encryption_algorithm_from_schannel(info.cipher),
+            key_exchange_algorithm: key_exchange_algorithm_from_schannel(info.key_exchange),
+            hash_algorithm: hash_algorithm_from_schannel(info.hash),
+        })
+    }
+
     pub fn tls_server_end_point(&self) -> Result<Option<Vec<u8>>, Error> {
         let cert = if self.0.is_server() {
             self.0.certificate()
@@ -534,9 +943,116 @@ impl<S: io::Read + io::Write> io::Write for TlsStream<S> {
 
 #[cfg(test)]
 mod tests {
-    use std::net::TcpStream;
+    use std::io::{self, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    use crate::{Certificate, Identity, TlsAcceptor, TlsConnector};
+
+    // A three-level chain (root CA -> intermediate CA -> leaf) used to exercise the
+    // acceptor/connector loopback tests below without hitting the network. The root is handed
+    // to the connector as the sole trust anchor with the machine's own roots disabled, so these
+    // tests only pass if `TlsAcceptor` actually sends the intermediate along with the leaf.
+    const ROOT_CA_CERT_PEM: &str = include_str!("../../tests/fixtures/root_ca_cert.pem");
+    const INTERMEDIATE_CERT_PEM: &str = include_str!("../../tests/fixtures/intermediate_cert.pem");
+    const LEAF_CERT_PEM: &str = include_str!("../../tests/fixtures/leaf_cert.pem");
+    const LEAF_KEY_PEM: &str = include_str!("../../tests/fixtures/leaf_key.pkcs8.pem");
+
+    // A self-signed certificate used as its own CA, presented by the client during the mutual
+    // TLS test below.
+    const CLIENT_CERT_PEM: &str = include_str!("../../tests/fixtures/client_cert.pem");
+    const CLIENT_KEY_PEM: &str = include_str!("../../tests/fixtures/client_key.pkcs8.pem");
+
+    // An EC private key, used only to confirm `from_pkcs8` rejects non-RSA keys up front.
+    const EC_KEY_PEM: &str = include_str!("../../tests/fixtures/ec_key.pkcs8.pem");
+
+    fn server_identity() -> Identity {
+        let mut cert_chain = LEAF_CERT_PEM.to_owned();
+        cert_chain.push_str(INTERMEDIATE_CERT_PEM);
+        Identity::from_pkcs8(cert_chain.as_bytes(), LEAF_KEY_PEM.as_bytes()).unwrap()
+    }
 
-    use crate::TlsConnector;
+    #[test]
+    fn from_pkcs8_rejects_ec_keys() {
+        let err =
+            Identity::from_pkcs8(LEAF_CERT_PEM.as_bytes(), EC_KEY_PEM.as_bytes()).unwrap_err();
+        assert_eq!(err.0.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn accept_connect_sends_chain_and_negotiates_alpn() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let acceptor = TlsAcceptor::builder(server_identity())
+            .request_alpns(&["h2"])
+            .build()
+            .unwrap();
+
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut stream = acceptor.accept(stream).unwrap();
+            let mut buf = [0; 5];
+            stream.read_exact(&mut buf).unwrap();
+            stream.write_all(&buf).unwrap();
+        });
+
+        let root = Certificate::from_pem(ROOT_CA_CERT_PEM.as_bytes()).unwrap();
+        let mut builder = TlsConnector::builder();
+        builder
+            .add_root_certificate(root)
+            .disable_built_in_roots(true)
+            .request_alpns(&["h2"]);
+        let connector = builder.build().unwrap();
+
+        let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let mut stream = connector.connect("localhost", stream).unwrap();
+
+        assert_eq!(stream.negotiated_alpn().unwrap(), Some(b"h2".to_vec()));
+        assert!(stream.protocol_version().unwrap().is_some());
+        assert!(stream.negotiated_cipher_suite().is_ok());
+
+        stream.write_all(b"hello").unwrap();
+        let mut buf = [0; 5];
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hello");
+
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn accept_requires_and_verifies_client_certificate() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let client_ca = Certificate::from_pem(CLIENT_CERT_PEM.as_bytes()).unwrap();
+        let mut acceptor_builder = TlsAcceptor::builder(server_identity());
+        acceptor_builder
+            .require_client_auth(true)
+            .add_client_root_certificate(client_ca);
+        let acceptor = acceptor_builder.build().unwrap();
+
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let stream = acceptor.accept(stream).unwrap();
+            assert!(stream.peer_certificate().unwrap().is_some());
+        });
+
+        let root = Certificate::from_pem(ROOT_CA_CERT_PEM.as_bytes()).unwrap();
+        let client_identity =
+            Identity::from_pkcs8(CLIENT_CERT_PEM.as_bytes(), CLIENT_KEY_PEM.as_bytes()).unwrap();
+        let mut builder = TlsConnector::builder();
+        builder
+            .identity(client_identity)
+            .add_root_certificate(root)
+            .disable_built_in_roots(true);
+        let connector = builder.build().unwrap();
+
+        let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        connector.connect("localhost", stream).unwrap();
+
+        server.join().unwrap();
+    }
 
     fn connect_and_assert(tls: &TlsConnector, domain: &str, port: u16, should_resume: bool) {
         let s = TcpStream::connect((domain, port)).unwrap();